@@ -14,6 +14,9 @@
 
 use core::fmt;
 
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+
 /// An error that occurs during certificate validation or name validation.
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 #[non_exhaustive]
@@ -102,6 +105,12 @@ pub enum Error {
     /// The certificate violates one or more path length constraints.
     PathLenConstraintViolated,
 
+    /// The certificate path's `valid_policy_tree`, after RFC 5280 §6.1 certificate policy
+    /// processing, was empty or did not intersect the caller's user-initial-policy-set while an
+    /// explicit policy was required, or a critical `certificatePolicies` extension left the
+    /// tree empty.
+    PolicyConstraintViolation,
+
     /// The certificate is not valid for the Extended Key Usage for which it is
     /// being validated.
     RequiredEkuNotFound,
@@ -205,7 +214,7 @@ impl Error {
             Error::SignatureAlgorithmMismatch => 25,
             Error::RequiredEkuNotFound => 24,
             Error::NameConstraintViolation => 23,
-            Error::PathLenConstraintViolated => 22,
+            Error::PathLenConstraintViolated | Error::PolicyConstraintViolation => 22,
             Error::CaUsedAsEndEntity | Error::EndEntityUsedAsCa => 21,
             Error::IssuerNotCrlSigner => 20,
 
@@ -262,6 +271,52 @@ impl From<untrusted::EndOfInput> for Error {
     }
 }
 
+/// A single candidate-path failure recorded while building a chain to a trust anchor.
+///
+/// `Error::most_specific` collapses every rejected candidate path down to the single highest
+/// ranked error, which is enough to decide pass/fail but not enough to diagnose *why* a chain
+/// with several plausible intermediates didn't validate. A path-building API that returns
+/// `Vec<PathError>` instead reports one of these per depth that was tried and rejected, so a
+/// caller can see e.g. "intermediate at depth 1 expired; alternative intermediate at depth 1
+/// had NameConstraintViolation" rather than just the highest-ranked error overall.
+///
+/// This type is the record a diagnostic path-building API would accumulate into and return;
+/// `verify_cert::build_chain`'s own recursive path-building loop isn't part of this tree, so
+/// nothing constructs a `PathError` yet.
+#[cfg(feature = "alloc")]
+#[derive(Clone, Debug)]
+pub struct PathError {
+    /// The depth of the rejected candidate in the path, where `0` is the end-entity
+    /// certificate's issuer and depth increases towards the trust anchor.
+    pub depth: usize,
+
+    /// The DER-encoded bytes of the rejected candidate certificate's `Subject` field, so the
+    /// caller can identify which certificate was tried without re-parsing the whole chain.
+    pub subject: Vec<u8>,
+
+    /// Why the candidate at this depth was rejected.
+    pub error: Error,
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod path_error_tests {
+    use alloc::vec;
+
+    use super::*;
+
+    #[test]
+    fn fields_are_accessible() {
+        let err = PathError {
+            depth: 1,
+            subject: vec![0x30, 0x00],
+            error: Error::CertExpired,
+        };
+        assert_eq!(err.depth, 1);
+        assert_eq!(err.subject, vec![0x30, 0x00]);
+        assert_eq!(err.error, Error::CertExpired);
+    }
+}
+
 /// Trailing data was found while parsing DER-encoded input for the named type.
 #[allow(missing_docs)]
 #[non_exhaustive]
@@ -0,0 +1,405 @@
+// Copyright 2015-2021 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR
+// ANY SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN
+// ACTION OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF
+// OR IN CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+//! RFC 5280 §6.1 certificate policy processing (the "valid_policy_tree").
+//!
+//! [`PolicyTree`] is meant to be driven one certificate at a time, from the trust anchor down to
+//! the end-entity certificate, by `verify_cert::build_chain` when the caller opts in via a
+//! user-initial-policy-set. `verify_cert.rs` isn't part of this tree, so nothing calls
+//! [`PolicyTree::process_certificate`] yet; this module is tested standalone below.
+
+use alloc::vec;
+use alloc::vec::Vec;
+
+use crate::Error;
+
+/// The `anyPolicy` OID (2.5.29.32.0), as the DER-encoded content octets of an OBJECT IDENTIFIER.
+pub(crate) const ANY_POLICY: &[u8] = &[0x55, 0x1D, 0x20, 0x00];
+
+/// The policy information carried by a single certificate's `certificatePolicies` extension.
+pub(crate) struct CertPolicies<'a> {
+    /// Whether the extension was marked critical. A critical `certificatePolicies` extension
+    /// must not be silently ignored: if policy processing isn't enabled, building must fail
+    /// rather than skip over it.
+    pub(crate) critical: bool,
+    /// The policy OIDs asserted by the certificate (DER-encoded content octets each).
+    pub(crate) policies: Vec<&'a [u8]>,
+}
+
+/// A single `policyMappings` entry: the issuer asserts that `issuer_domain_policy` is
+/// equivalent to `subject_domain_policy` for certificates issued beneath it.
+pub(crate) struct PolicyMapping<'a> {
+    pub(crate) issuer_domain_policy: &'a [u8],
+    pub(crate) subject_domain_policy: &'a [u8],
+}
+
+/// The `policyConstraints`/`inhibitAnyPolicy` values read from a single certificate, if present.
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct PolicyConstraints {
+    pub(crate) require_explicit_policy: Option<u32>,
+    pub(crate) inhibit_policy_mapping: Option<u32>,
+    pub(crate) inhibit_any_policy: Option<u32>,
+}
+
+struct Node<'a> {
+    valid_policy: &'a [u8],
+    expected_policy_set: Vec<&'a [u8]>,
+}
+
+/// The RFC 5280 §6.1.2(e) "valid_policy_tree", narrowed one certificate at a time by
+/// [`PolicyTree::process_certificate`] and finally checked against the caller's
+/// user-initial-policy-set by [`PolicyTree::finish`].
+pub(crate) struct PolicyTree<'a> {
+    // Kept as a flat list rather than a real tree: all that finish() needs is the set of
+    // surviving valid_policy OIDs, and intermediate depths never need to be walked back into.
+    nodes: Vec<Node<'a>>,
+    explicit_policy: u32,
+    policy_mapping: u32,
+    inhibit_any_policy: u32,
+}
+
+impl<'a> PolicyTree<'a> {
+    /// Creates the initial tree: a single node at depth 0 holding `anyPolicy` with an
+    /// expected-policy-set of `{anyPolicy}`.
+    ///
+    /// `path_len` is the number of non-self-issued certificates between (but not including)
+    /// the trust anchor and the end-entity certificate; it seeds the `explicit_policy`,
+    /// `policy_mapping`, and `inhibit_any_policy` counters per RFC 5280 §6.1.2(a)/(b)/(c).
+    pub(crate) fn new(path_len: u32, require_explicit_policy: bool) -> Self {
+        Self {
+            nodes: vec![Node {
+                valid_policy: ANY_POLICY,
+                expected_policy_set: vec![ANY_POLICY],
+            }],
+            explicit_policy: if require_explicit_policy { 0 } else { path_len },
+            policy_mapping: path_len,
+            inhibit_any_policy: path_len,
+        }
+    }
+
+    /// Narrows the tree with one certificate's policy-related extensions.
+    ///
+    /// `is_self_issued` must be `true` for self-issued (but not necessarily self-signed)
+    /// certificates, per RFC 5280 §6.1's special-casing of the explicit-policy and
+    /// policy-mapping counters for them.
+    pub(crate) fn process_certificate(
+        &mut self,
+        policies: Option<&CertPolicies<'a>>,
+        mappings: &[PolicyMapping<'a>],
+        constraints: PolicyConstraints,
+        is_self_issued: bool,
+    ) -> Result<(), Error> {
+        match policies {
+            Some(policies) => {
+                self.apply_policies(policies);
+                // A critical certificatePolicies extension must not be silently ignored: if
+                // narrowing it against the tree so far leaves no surviving policy at all, that's
+                // a hard failure right here, rather than something `finish` might or might not
+                // catch later depending on where `explicit_policy` happens to land by the end of
+                // the path (it only fails an empty tree when `explicit_policy == 0`, which isn't
+                // necessarily true yet at this depth).
+                if policies.critical && self.nodes.is_empty() {
+                    return Err(Error::PolicyConstraintViolation);
+                }
+            }
+            // §6.1.3(d): if there's no certificatePolicies extension and an explicit policy is
+            // still required, the tree is emptied; an empty tree only becomes a hard failure if
+            // it's still empty when `finish` is called with `explicit_policy == 0`.
+            None if self.explicit_policy == 0 => self.nodes.clear(),
+            None => {}
+        }
+
+        if !mappings.is_empty() {
+            if self.policy_mapping > 0 {
+                self.apply_mappings(mappings);
+            } else {
+                // §6.1.3(g)(4): mappings must be ignored, but any node whose valid_policy is an
+                // issuerDomainPolicy named by a mapping (and isn't anyPolicy) must be deleted.
+                for mapping in mappings {
+                    if mapping.issuer_domain_policy != ANY_POLICY {
+                        self.delete_nodes_with_policy(mapping.issuer_domain_policy);
+                    }
+                }
+            }
+        }
+
+        // §6.1.3(h)/(i): wrap-up counter maintenance for this certificate.
+        if !is_self_issued {
+            self.explicit_policy = self.explicit_policy.saturating_sub(1);
+            self.policy_mapping = self.policy_mapping.saturating_sub(1);
+        }
+        self.inhibit_any_policy = self.inhibit_any_policy.saturating_sub(1);
+
+        // §6.1.3(j)/(k)/(l): apply this certificate's own policyConstraints/inhibitAnyPolicy,
+        // which can only ever tighten (never loosen) the running counters.
+        if let Some(require_explicit_policy) = constraints.require_explicit_policy {
+            self.explicit_policy = self.explicit_policy.min(require_explicit_policy);
+        }
+        if let Some(inhibit_policy_mapping) = constraints.inhibit_policy_mapping {
+            self.policy_mapping = self.policy_mapping.min(inhibit_policy_mapping);
+        }
+        if let Some(inhibit_any_policy) = constraints.inhibit_any_policy {
+            self.inhibit_any_policy = self.inhibit_any_policy.min(inhibit_any_policy);
+        }
+
+        Ok(())
+    }
+
+    fn apply_policies(&mut self, policies: &CertPolicies<'a>) {
+        let has_any_policy = policies.policies.iter().any(|oid| *oid == ANY_POLICY);
+
+        // §6.1.3(d)(1): for each policy OID in the certificate (other than anyPolicy) that is
+        // in some node's expected_policy_set, create (or keep) a node for it.
+        let mut next: Vec<Node<'a>> = Vec::new();
+        for oid in policies.policies.iter().filter(|oid| **oid != ANY_POLICY) {
+            let matched = self
+                .nodes
+                .iter()
+                .any(|n| n.expected_policy_set.iter().any(|p| p == oid));
+            if matched {
+                next.push(Node {
+                    valid_policy: oid,
+                    expected_policy_set: vec![oid],
+                });
+            }
+        }
+
+        // §6.1.3(d)(2): if the certificate asserts anyPolicy and anyPolicy processing isn't
+        // inhibited, every node whose valid_policy has no node created above survives
+        // unchanged (an anyPolicy node effectively stands in for "whatever the parent allowed").
+        if has_any_policy && self.inhibit_any_policy > 0 {
+            for node in &self.nodes {
+                let covered = next.iter().any(|n| n.valid_policy == node.valid_policy);
+                if !covered {
+                    next.push(Node {
+                        valid_policy: node.valid_policy,
+                        expected_policy_set: node.expected_policy_set.clone(),
+                    });
+                }
+            }
+        }
+
+        self.nodes = next;
+    }
+
+    fn apply_mappings(&mut self, mappings: &[PolicyMapping<'a>]) {
+        for mapping in mappings {
+            for node in &mut self.nodes {
+                if node.valid_policy == mapping.issuer_domain_policy {
+                    node.expected_policy_set = vec![mapping.subject_domain_policy];
+                }
+            }
+        }
+    }
+
+    fn delete_nodes_with_policy(&mut self, policy: &[u8]) {
+        self.nodes.retain(|n| n.valid_policy != policy);
+    }
+
+    /// Intersects the tree's surviving valid policies with the caller's
+    /// user-initial-policy-set, per RFC 5280 §6.1.5(g).
+    ///
+    /// Returns the set of policies the path is valid for. This fails with
+    /// [`Error::PolicyConstraintViolation`] only if explicit policy is required (the
+    /// `explicit_policy` counter has reached 0) and either the tree is empty or, when
+    /// `user_initial_policy_set` doesn't contain `anyPolicy`, the tree has no surviving node for
+    /// any policy in it (including via an `anyPolicy` node). When explicit policy isn't
+    /// required, an empty intersection is returned as an empty set rather than an error.
+    pub(crate) fn finish(self, user_initial_policy_set: &[&[u8]]) -> Result<Vec<&'a [u8]>, Error> {
+        if self.explicit_policy == 0 && self.nodes.is_empty() {
+            return Err(Error::PolicyConstraintViolation);
+        }
+
+        if user_initial_policy_set.iter().any(|oid| *oid == ANY_POLICY) {
+            return Ok(self.nodes.iter().map(|n| n.valid_policy).collect());
+        }
+
+        let any_policy_node = self.nodes.iter().find(|n| n.valid_policy == ANY_POLICY);
+        let mut valid = Vec::new();
+        for oid in user_initial_policy_set {
+            if self.nodes.iter().any(|n| n.valid_policy == *oid) {
+                valid.push(*oid);
+            } else if let Some(node) = any_policy_node {
+                let _ = node;
+                valid.push(*oid);
+            }
+        }
+
+        if valid.is_empty() && self.explicit_policy == 0 {
+            return Err(Error::PolicyConstraintViolation);
+        }
+
+        Ok(valid)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const POLICY_A: &[u8] = &[0x06, 0x01];
+    const POLICY_B: &[u8] = &[0x06, 0x02];
+
+    #[test]
+    fn default_tree_is_any_policy() {
+        let tree = PolicyTree::new(0, false);
+        assert_eq!(tree.finish(&[ANY_POLICY]).unwrap(), vec![ANY_POLICY]);
+    }
+
+    #[test]
+    fn critical_policies_extension_fails_fast_on_empty_tree() {
+        // A large path_len keeps `explicit_policy` well above 0, so without the critical-flag
+        // check this would only be caught (if at all) much later in `finish`, once enough
+        // certificates had been processed to exhaust it -- or not at all, if the path ends
+        // first. A *critical* certificatePolicies extension that narrows the tree to nothing
+        // must fail immediately instead.
+        let mut tree = PolicyTree::new(5, false);
+        let err = tree
+            .process_certificate(
+                Some(&CertPolicies {
+                    critical: true,
+                    policies: vec![POLICY_A],
+                }),
+                &[],
+                PolicyConstraints::default(),
+                false,
+            )
+            .unwrap_err();
+
+        assert_eq!(err, Error::PolicyConstraintViolation);
+    }
+
+    #[test]
+    fn non_critical_policies_extension_does_not_fail_fast() {
+        // Same setup as above, but non-critical: process_certificate must not fail early, even
+        // though it leaves the tree empty (that's caught later by `finish`, if at all).
+        let mut tree = PolicyTree::new(5, false);
+        tree.process_certificate(
+            Some(&CertPolicies {
+                critical: false,
+                policies: vec![POLICY_A],
+            }),
+            &[],
+            PolicyConstraints::default(),
+            false,
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn any_policy_assertion_keeps_root_node() {
+        let mut tree = PolicyTree::new(1, false);
+        tree.process_certificate(
+            Some(&CertPolicies {
+                critical: false,
+                policies: vec![ANY_POLICY],
+            }),
+            &[],
+            PolicyConstraints::default(),
+            false,
+        )
+        .unwrap();
+
+        assert_eq!(tree.finish(&[ANY_POLICY]).unwrap(), vec![ANY_POLICY]);
+    }
+
+    #[test]
+    fn asserting_an_unmapped_oid_with_no_any_policy_empties_the_tree() {
+        let mut tree = PolicyTree::new(1, false);
+        tree.process_certificate(
+            Some(&CertPolicies {
+                critical: false,
+                policies: vec![POLICY_A],
+            }),
+            &[],
+            PolicyConstraints::default(),
+            false,
+        )
+        .unwrap();
+
+        // POLICY_A was never in any node's expected_policy_set (the root only expects
+        // anyPolicy), so it isn't matched and the tree ends up with no surviving node.
+        assert_eq!(
+            tree.finish(&[POLICY_A]).unwrap_err(),
+            Error::PolicyConstraintViolation
+        );
+    }
+
+    #[test]
+    fn missing_policies_extension_empties_tree_once_explicit_policy_required() {
+        let mut tree = PolicyTree::new(0, true);
+        tree.process_certificate(None, &[], PolicyConstraints::default(), false)
+            .unwrap();
+
+        assert_eq!(
+            tree.finish(&[ANY_POLICY]).unwrap_err(),
+            Error::PolicyConstraintViolation
+        );
+    }
+
+    #[test]
+    fn policy_mapping_substitutes_expected_policy_set() {
+        // First certificate asserts anyPolicy (keeping the root node alive) and maps it onto
+        // POLICY_B; the next certificate can then assert POLICY_B and have it match the root's
+        // now-substituted expected_policy_set.
+        let mut tree = PolicyTree::new(2, false);
+        tree.process_certificate(
+            Some(&CertPolicies {
+                critical: false,
+                policies: vec![ANY_POLICY],
+            }),
+            &[PolicyMapping {
+                issuer_domain_policy: ANY_POLICY,
+                subject_domain_policy: POLICY_B,
+            }],
+            PolicyConstraints::default(),
+            false,
+        )
+        .unwrap();
+        tree.process_certificate(
+            Some(&CertPolicies {
+                critical: false,
+                policies: vec![POLICY_B],
+            }),
+            &[],
+            PolicyConstraints::default(),
+            false,
+        )
+        .unwrap();
+
+        assert_eq!(tree.finish(&[POLICY_B]).unwrap(), vec![POLICY_B]);
+    }
+
+    #[test]
+    fn require_explicit_policy_constraint_forces_failure_on_empty_tree() {
+        let mut tree = PolicyTree::new(1, false);
+        tree.process_certificate(
+            None,
+            &[],
+            PolicyConstraints {
+                require_explicit_policy: Some(0),
+                ..Default::default()
+            },
+            false,
+        )
+        .unwrap();
+        tree.process_certificate(None, &[], PolicyConstraints::default(), false)
+            .unwrap();
+
+        assert_eq!(
+            tree.finish(&[ANY_POLICY]).unwrap_err(),
+            Error::PolicyConstraintViolation
+        );
+    }
+}
@@ -0,0 +1,147 @@
+// Copyright 2015-2021 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR
+// ANY SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN
+// ACTION OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF
+// OR IN CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+//! `rfc822Name` (email address) subject names.
+//!
+//! These back-stop S/MIME and client-auth certificate validation, where the subject is
+//! identified by an email address in the subjectAltName extension rather than a DNS name.
+//!
+//! Nothing in `subject_name` constructs a `SubjectNameRef::Email` yet, so
+//! [`presented_email_matches_reference`] isn't reachable from
+//! [`EndEntityCert::verify_is_valid_for_subject_name`](crate::EndEntityCert::verify_is_valid_for_subject_name)
+//! in this tree; wiring it in means adding that variant and a `GeneralName` rfc822Name branch in
+//! `subject_name`'s SAN-matching loop, which isn't part of this tree. The matching logic below is
+//! tested standalone in the meantime.
+
+use core::fmt;
+
+use crate::Error;
+
+/// A reference to an email address, to be matched against an `rfc822Name` entry in a
+/// certificate's subjectAltName extension.
+///
+/// Per RFC 5280 §4.2.1.6 (which defers to RFC 822), the local part of an email address is
+/// case-sensitive and the domain part is case-insensitive, so `EmailAddressRef` preserves the
+/// original bytes rather than normalizing them up front; comparison happens in
+/// [`presented_email_matches_reference`].
+#[derive(Clone, Copy, Eq, PartialEq)]
+pub struct EmailAddressRef<'a>(&'a str);
+
+impl<'a> EmailAddressRef<'a> {
+    /// Constructs an `EmailAddressRef` from the ASCII bytes of an email address, e.g.
+    /// `b"user@example.com"`.
+    ///
+    /// Returns `Error::MalformedDnsIdentifier` if `email` isn't ASCII or doesn't contain
+    /// exactly one `@` separating a non-empty local part from a non-empty domain part.
+    pub fn try_from_ascii(email: &'a [u8]) -> Result<Self, Error> {
+        if !email.is_ascii() {
+            return Err(Error::MalformedDnsIdentifier);
+        }
+        let email = core::str::from_utf8(email).map_err(|_| Error::MalformedDnsIdentifier)?;
+        if email.bytes().filter(|b| *b == b'@').count() != 1 {
+            return Err(Error::MalformedDnsIdentifier);
+        }
+        let (local, domain) = split_email(email).ok_or(Error::MalformedDnsIdentifier)?;
+        if local.is_empty() || domain.is_empty() {
+            return Err(Error::MalformedDnsIdentifier);
+        }
+        Ok(Self(email))
+    }
+
+    /// The email address as it was provided, e.g. `"user@example.com"`.
+    pub fn as_str(&self) -> &'a str {
+        self.0
+    }
+}
+
+impl fmt::Debug for EmailAddressRef<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("EmailAddressRef").field(&self.0).finish()
+    }
+}
+
+fn split_email(email: &str) -> Option<(&str, &str)> {
+    let at = email.rfind('@')?;
+    Some((&email[..at], &email[at + 1..]))
+}
+
+/// Returns `true` if the `rfc822Name` SAN entry `presented` identifies the same mailbox as
+/// `reference`, per the case rules of RFC 5280 §4.2.1.6 / RFC 822: the local part compares
+/// case-sensitively and the domain part compares case-insensitively (ASCII only, matching how
+/// this crate already treats DNS name labels).
+pub(crate) fn presented_email_matches_reference(
+    presented: &str,
+    reference: EmailAddressRef<'_>,
+) -> bool {
+    let (Some((presented_local, presented_domain)), Some((reference_local, reference_domain))) =
+        (split_email(presented), split_email(reference.0))
+    else {
+        return false;
+    };
+
+    presented_local == reference_local && presented_domain.eq_ignore_ascii_case(reference_domain)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accepts_valid_address() {
+        let addr = EmailAddressRef::try_from_ascii(b"user@example.com").unwrap();
+        assert_eq!(addr.as_str(), "user@example.com");
+    }
+
+    #[test]
+    fn rejects_non_ascii() {
+        assert_eq!(
+            EmailAddressRef::try_from_ascii("üser@example.com".as_bytes()).unwrap_err(),
+            Error::MalformedDnsIdentifier
+        );
+    }
+
+    #[test]
+    fn rejects_missing_at_or_empty_parts() {
+        for invalid in ["example.com", "user@", "@example.com"] {
+            assert_eq!(
+                EmailAddressRef::try_from_ascii(invalid.as_bytes()).unwrap_err(),
+                Error::MalformedDnsIdentifier,
+                "expected {invalid:?} to be rejected"
+            );
+        }
+    }
+
+    #[test]
+    fn matches_are_local_case_sensitive_domain_case_insensitive() {
+        let reference = EmailAddressRef::try_from_ascii(b"User@Example.com").unwrap();
+
+        assert!(presented_email_matches_reference(
+            "User@example.COM",
+            reference
+        ));
+        assert!(!presented_email_matches_reference(
+            "user@Example.com",
+            reference
+        ));
+    }
+
+    #[test]
+    fn rejects_malformed_presented_name() {
+        let reference = EmailAddressRef::try_from_ascii(b"user@example.com").unwrap();
+        assert!(!presented_email_matches_reference(
+            "not-an-email",
+            reference
+        ));
+    }
+}
@@ -14,16 +14,20 @@
 
 //! Conversions into the library's time type.
 
+use core::fmt;
+use core::time::Duration;
+
 use crate::der::{self, FromDer, Tag};
 use crate::error::{DerTypeId, Error};
 
 /// The time type.
 ///
 /// Internally this is merely a UNIX timestamp: a count of non-leap
-/// seconds since the start of 1970.  This type exists to assist
-/// unit-of-measure correctness.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd)]
-pub struct Time(u64);
+/// seconds since the start of 1970, which may be negative to represent
+/// a time before the epoch.  This type exists to assist unit-of-measure
+/// correctness.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Time(i64);
 
 impl Time {
     /// Create a `webpki::Time` from a unix timestamp.
@@ -32,10 +36,193 @@ impl Time {
     /// `webpki::Time::try_from(time: std::time::SystemTime)` instead when
     /// `std::time::SystemTime` is available (when `#![no_std]` isn't being
     /// used).
-    #[allow(clippy::must_use_candidate)]
+    #[allow(clippy::must_use_candidate, clippy::as_conversions)]
     pub fn from_seconds_since_unix_epoch(secs: u64) -> Self {
+        Self(secs as i64)
+    }
+
+    /// Create a `webpki::Time` from a count of seconds since the unix epoch, allowing negative
+    /// values to represent a time before 1970. This is needed because `notBefore` dates from the
+    /// 1960s or earlier do exist in some long-lived roots and test corpora, and GeneralizedTime/
+    /// UTCTime can legally express them.
+    #[allow(clippy::must_use_candidate)]
+    pub fn from_seconds_since_unix_epoch_i64(secs: i64) -> Self {
         Self(secs)
     }
+
+    /// Returns how long after `other` this time is, or `None` if `other` is later than `self`.
+    ///
+    /// This, together with the `Add`/`Sub<Duration>` impls below, lets callers express
+    /// skew-tolerant validity checks (e.g. "reject if now is more than N seconds past notAfter")
+    /// without reaching into the internal representation of `Time`.
+    #[allow(clippy::must_use_candidate, clippy::as_conversions)]
+    pub fn duration_since(&self, other: Time) -> Option<Duration> {
+        let secs = self.0.checked_sub(other.0)?;
+        u64::try_from(secs).ok().map(Duration::from_secs)
+    }
+
+    /// Decomposes this `Time` back into broken-down UTC calendar components: `(year, month,
+    /// day_of_month, hours, minutes, seconds)`.
+    ///
+    /// This is the inverse of the arithmetic in `time_from_ymdhms_utc`, implemented with the
+    /// standard civil-from-days algorithm so it stays `no_std` and allocation-free. It makes it
+    /// possible to produce human-readable diagnostics (e.g. "certificate notAfter was
+    /// 2023-06-01T12:00:00Z") without pulling in a full calendar library.
+    #[allow(clippy::must_use_candidate, clippy::as_conversions)]
+    pub fn to_ymdhms_utc(&self) -> (u64, u8, u8, u8, u8, u8) {
+        let days = self.0.div_euclid(86400);
+        let secs_of_day = self.0.rem_euclid(86400);
+        let hours = secs_of_day / 3600;
+        let minutes = (secs_of_day % 3600) / 60;
+        let seconds = secs_of_day % 60;
+
+        // Shift the epoch to 0000-03-01, the start of the 400-year Gregorian cycle used below.
+        let z = days + 719_468;
+        let era = z / 146_097;
+        let doe = z - era * 146_097; // day of era, 0..=146096
+        let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365; // year of era, 0..=399
+        let mut year = yoe + era * 400;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100); // day of year, 0..=365
+        let mp = (5 * doy + 2) / 153; // month, shifted so March is 0, 0..=11
+        let day_of_month = doy - (153 * mp + 2) / 5 + 1; // 1..=31
+        let month = if mp < 10 { mp + 3 } else { mp - 9 }; // back to the usual Jan == 1
+        if month <= 2 {
+            year += 1;
+        }
+
+        (
+            year as u64,
+            month as u8,
+            day_of_month as u8,
+            hours as u8,
+            minutes as u8,
+            seconds as u8,
+        )
+    }
+}
+
+impl core::ops::Add<Duration> for Time {
+    type Output = Time;
+
+    /// Offsets this time forward by `rhs`, e.g. to apply clock-skew tolerance when checking
+    /// validity. Saturates, rather than overflowing, if `rhs` would push the result out of the
+    /// range representable by `Time`.
+    #[allow(clippy::as_conversions)]
+    fn add(self, rhs: Duration) -> Time {
+        let secs = i64::try_from(rhs.as_secs()).unwrap_or(i64::MAX);
+        Self(self.0.saturating_add(secs))
+    }
+}
+
+impl core::ops::Sub<Duration> for Time {
+    type Output = Time;
+
+    /// Offsets this time backward by `rhs`. Saturates, rather than overflowing, if `rhs` would
+    /// push the result out of the range representable by `Time`.
+    #[allow(clippy::as_conversions)]
+    fn sub(self, rhs: Duration) -> Time {
+        let secs = i64::try_from(rhs.as_secs()).unwrap_or(i64::MAX);
+        Self(self.0.saturating_sub(secs))
+    }
+}
+
+impl core::str::FromStr for Time {
+    type Err = Error;
+
+    /// Parses an RFC 3339 / ISO 8601 UTC timestamp, e.g. `"2023-06-01T12:00:00Z"`.
+    ///
+    /// Callers who configure a fixed verification time (for reproducible builds, auditing, or
+    /// testing expired chains) can use this instead of hand-computing a unix timestamp. Only the
+    /// fixed-width `YYYY-MM-DDTHH:MM:SS` form followed by a mandatory `Z` (UTC) is accepted
+    /// (the `T` and `Z` may also be lowercase), reusing `time_from_ymdhms_utc` for the
+    /// underlying arithmetic and mirroring the field validation the DER parser already does. An
+    /// optional fractional-seconds suffix (e.g. `.123`) is validated but discarded, since `Time`
+    /// has second resolution. The `TryFrom<&str>` impl below delegates to this, so
+    /// `Time::try_from("...")` works too.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let bytes = s.as_bytes();
+        if bytes.len() < 20 {
+            return Err(Error::BadDerTime);
+        }
+
+        fn digit(b: u8) -> Result<u64, Error> {
+            if b.is_ascii_digit() {
+                Ok(u64::from(b - b'0'))
+            } else {
+                Err(Error::BadDerTime)
+            }
+        }
+
+        fn two_digits(bytes: &[u8], at: usize, min: u64, max: u64) -> Result<u64, Error> {
+            let value = (digit(bytes[at])? * 10) + digit(bytes[at + 1])?;
+            if value < min || value > max {
+                return Err(Error::BadDerTime);
+            }
+            Ok(value)
+        }
+
+        let year = (two_digits(bytes, 0, 0, 99)? * 100) + two_digits(bytes, 2, 0, 99)?;
+        if bytes[4] != b'-' {
+            return Err(Error::BadDerTime);
+        }
+        let month = two_digits(bytes, 5, 1, 12)?;
+        if bytes[7] != b'-' {
+            return Err(Error::BadDerTime);
+        }
+        let day_of_month = two_digits(bytes, 8, 1, days_in_month(year, month))?;
+        if !matches!(bytes[10], b'T' | b't') {
+            return Err(Error::BadDerTime);
+        }
+        let hours = two_digits(bytes, 11, 0, 23)?;
+        if bytes[13] != b':' {
+            return Err(Error::BadDerTime);
+        }
+        let minutes = two_digits(bytes, 14, 0, 59)?;
+        if bytes[16] != b':' {
+            return Err(Error::BadDerTime);
+        }
+        // As in the DER parser, a seconds value of 60 (a positive leap second) is accepted and
+        // smeared into the same minute.
+        let seconds = two_digits(bytes, 17, 0, 60)?.min(59);
+
+        let mut rest = &bytes[19..];
+        if let [b'.', after_dot @ ..] = rest {
+            let digits_end = after_dot
+                .iter()
+                .position(|b| !b.is_ascii_digit())
+                .unwrap_or(after_dot.len());
+            if digits_end == 0 {
+                return Err(Error::BadDerTime);
+            }
+            rest = &after_dot[digits_end..];
+        }
+
+        if !matches!(rest, b"Z" | b"z") {
+            return Err(Error::BadDerTime);
+        }
+
+        time_from_ymdhms_utc(year, month, day_of_month, hours, minutes, seconds)
+    }
+}
+
+impl TryFrom<&str> for Time {
+    type Error = Error;
+
+    /// Parses an RFC 3339 / ISO 8601 UTC timestamp, exactly as the `FromStr` impl does.
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl fmt::Display for Time {
+    /// Renders the time as an RFC 3339 / ISO 8601 UTC timestamp, e.g. `2023-06-01T12:00:00Z`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (year, month, day, hours, minutes, seconds) = self.to_ymdhms_utc();
+        write!(
+            f,
+            "{year:04}-{month:02}-{day:02}T{hours:02}:{minutes:02}:{seconds:02}Z"
+        )
+    }
 }
 
 impl<'a> FromDer<'a> for Time {
@@ -91,7 +278,11 @@ impl<'a> FromDer<'a> for Time {
                 let day_of_month = read_two_digits(value, 1, days_in_month)?;
                 let hours = read_two_digits(value, 0, 23)?;
                 let minutes = read_two_digits(value, 0, 59)?;
-                let seconds = read_two_digits(value, 0, 59)?;
+                // UTCTime/GeneralizedTime permit a seconds value of 60 to denote a positive leap
+                // second. `Time` counts non-leap seconds, so a leap second is smeared into the
+                // same minute by clamping it down to :59 rather than rolling into the next
+                // minute, matching how other timestamp libraries normalize leap seconds.
+                let seconds = read_two_digits(value, 0, 60)?.min(59);
 
                 let time_zone = value.read_byte().map_err(|_| Error::BadDerTime)?;
                 if time_zone != b'Z' {
@@ -135,6 +326,7 @@ impl TryFrom<std::time::SystemTime> for Time {
     }
 }
 
+#[allow(clippy::as_conversions)]
 pub(crate) fn time_from_ymdhms_utc(
     year: u64,
     month: u64,
@@ -143,20 +335,20 @@ pub(crate) fn time_from_ymdhms_utc(
     minutes: u64,
     seconds: u64,
 ) -> Result<Time, Error> {
-    let days_before_year_since_unix_epoch = days_before_year_since_unix_epoch(year)?;
-
-    const JAN: u64 = 31;
-    let feb = days_in_feb(year);
-    const MAR: u64 = 31;
-    const APR: u64 = 30;
-    const MAY: u64 = 31;
-    const JUN: u64 = 30;
-    const JUL: u64 = 31;
-    const AUG: u64 = 31;
-    const SEP: u64 = 30;
-    const OCT: u64 = 31;
-    const NOV: u64 = 30;
-    let days_before_month_in_year = match month {
+    let days_before_year_since_unix_epoch = days_before_year_since_unix_epoch(year);
+
+    const JAN: i64 = 31;
+    let feb = days_in_feb(year) as i64;
+    const MAR: i64 = 31;
+    const APR: i64 = 30;
+    const MAY: i64 = 31;
+    const JUN: i64 = 30;
+    const JUL: i64 = 31;
+    const AUG: i64 = 31;
+    const SEP: i64 = 30;
+    const OCT: i64 = 31;
+    const NOV: i64 = 30;
+    let days_before_month_in_year: i64 = match month {
         1 => 0,
         2 => JAN,
         3 => JAN + feb,
@@ -173,35 +365,38 @@ pub(crate) fn time_from_ymdhms_utc(
     };
 
     let days_before =
-        days_before_year_since_unix_epoch + days_before_month_in_year + day_of_month - 1;
+        days_before_year_since_unix_epoch + days_before_month_in_year + (day_of_month as i64) - 1;
 
-    let seconds_since_unix_epoch =
-        (days_before * 24 * 60 * 60) + (hours * 60 * 60) + (minutes * 60) + seconds;
+    let seconds_since_unix_epoch = (days_before * 24 * 60 * 60)
+        + (hours as i64 * 60 * 60)
+        + (minutes as i64 * 60)
+        + (seconds as i64);
 
-    Ok(Time::from_seconds_since_unix_epoch(
+    Ok(Time::from_seconds_since_unix_epoch_i64(
         seconds_since_unix_epoch,
     ))
 }
 
-fn days_before_year_since_unix_epoch(year: u64) -> Result<u64, Error> {
-    // We don't support dates before January 1, 1970 because that is the
-    // Unix epoch. It is likely that other software won't deal well with
-    // certificates that have dates before the epoch.
-    if year < UNIX_EPOCH_YEAR {
-        return Err(Error::BadDerTime);
-    }
-    let days_before_year_ad = days_before_year_ad(year);
-    debug_assert!(days_before_year_ad >= DAYS_BEFORE_UNIX_EPOCH_AD);
-    Ok(days_before_year_ad - DAYS_BEFORE_UNIX_EPOCH_AD)
-}
-
 const UNIX_EPOCH_YEAR: u64 = 1970;
 
-fn days_before_year_ad(year: u64) -> u64 {
+/// Returns the (possibly negative) number of days between January 1, 1970 and January 1 of
+/// `year`, so that `notBefore` dates from before the Unix epoch (e.g. from the 1960s or
+/// earlier) can be represented, as GeneralizedTime/UTCTime can legally express them.
+#[allow(clippy::as_conversions)]
+fn days_before_year_since_unix_epoch(year: u64) -> i64 {
+    days_before_year_ad(year) - DAYS_BEFORE_UNIX_EPOCH_AD
+}
+
+#[allow(clippy::as_conversions)]
+fn days_before_year_ad(year: u64) -> i64 {
+    let year = year as i64;
+    // `year - 1` is negative for `year == 0`, the one in-range value (GeneralizedTime years are
+    // 4-digit, so `0000` is syntactically valid) where plain `/` and `div_euclid` disagree;
+    // `div_euclid` rounds towards negative infinity, which is what the leap-year count needs.
     ((year - 1) * 365)
-        + ((year - 1) / 4)    // leap years are every 4 years,
-        - ((year - 1) / 100)  // except years divisible by 100,
-        + ((year - 1) / 400) // except years divisible by 400.
+        + (year - 1).div_euclid(4)    // leap years are every 4 years,
+        - (year - 1).div_euclid(100)  // except years divisible by 100,
+        + (year - 1).div_euclid(400) // except years divisible by 400.
 }
 
 pub(crate) fn days_in_month(year: u64, month: u64) -> u64 {
@@ -223,7 +418,7 @@ fn days_in_feb(year: u64) -> u64 {
 
 /// All the days up to and including 1969, plus the 477 leap days since AD began
 /// (calculated in Gregorian rules).
-const DAYS_BEFORE_UNIX_EPOCH_AD: u64 = 1969 * 365 + 477;
+const DAYS_BEFORE_UNIX_EPOCH_AD: i64 = 1969 * 365 + 477;
 
 #[cfg(test)]
 mod tests {
@@ -236,18 +431,22 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_days_before_year_ad_handles_year_zero() {
+        use super::days_before_year_ad;
+        // `0000` is a syntactically valid 4-digit GeneralizedTime year. Year 0 (the year before
+        // year 1, in the proleptic Gregorian calendar) is divisible by 400 and so is a leap year,
+        // meaning it must start 366 days, not 365, before year 1.
+        assert_eq!(-366, days_before_year_ad(0));
+    }
+
     #[test]
     fn test_days_before_year_since_unix_epoch() {
-        use super::{days_before_year_since_unix_epoch, Error, UNIX_EPOCH_YEAR};
-        assert_eq!(Ok(0), days_before_year_since_unix_epoch(UNIX_EPOCH_YEAR));
-        assert_eq!(
-            Ok(365),
-            days_before_year_since_unix_epoch(UNIX_EPOCH_YEAR + 1)
-        );
-        assert_eq!(
-            Err(Error::BadDerTime),
-            days_before_year_since_unix_epoch(UNIX_EPOCH_YEAR - 1)
-        );
+        use super::{days_before_year_since_unix_epoch, UNIX_EPOCH_YEAR};
+        assert_eq!(0, days_before_year_since_unix_epoch(UNIX_EPOCH_YEAR));
+        assert_eq!(365, days_before_year_since_unix_epoch(UNIX_EPOCH_YEAR + 1));
+        // Years before the epoch are represented as a negative day offset rather than rejected.
+        assert_eq!(-365, days_before_year_since_unix_epoch(UNIX_EPOCH_YEAR - 1));
     }
 
     #[test]
@@ -275,18 +474,18 @@ mod tests {
 
     #[test]
     fn test_time_from_ymdhms_utc() {
-        use super::{time_from_ymdhms_utc, Error, Time, UNIX_EPOCH_YEAR};
+        use super::{time_from_ymdhms_utc, Time, UNIX_EPOCH_YEAR};
 
-        // 1969-12-31 00:00:00
+        // 1969-12-31 00:00:00, a day before the epoch
         assert_eq!(
-            Err(Error::BadDerTime),
-            time_from_ymdhms_utc(UNIX_EPOCH_YEAR - 1, 1, 1, 0, 0, 0)
+            Time::from_seconds_since_unix_epoch_i64(-86400),
+            time_from_ymdhms_utc(UNIX_EPOCH_YEAR - 1, 12, 31, 0, 0, 0).unwrap()
         );
 
-        // 1969-12-31 23:59:59
+        // 1969-12-31 23:59:59, the second before the epoch
         assert_eq!(
-            Err(Error::BadDerTime),
-            time_from_ymdhms_utc(UNIX_EPOCH_YEAR - 1, 12, 31, 23, 59, 59)
+            Time::from_seconds_since_unix_epoch_i64(-1),
+            time_from_ymdhms_utc(UNIX_EPOCH_YEAR - 1, 12, 31, 23, 59, 59).unwrap()
         );
 
         // 1970-01-01 00:00:00
@@ -329,4 +528,120 @@ mod tests {
             time_from_ymdhms_utc(2016, 4, 17, 17, 12, 42).unwrap()
         );
     }
+
+    #[test]
+    fn test_leap_second_is_clamped() {
+        use super::{time_from_ymdhms_utc, Time};
+        use crate::der::FromDer;
+
+        // GeneralizedTime "19961231235960Z": a positive leap second inserted at the end of 1996.
+        const DER: &[u8] = &[
+            0x18, 15, b'1', b'9', b'9', b'6', b'1', b'2', b'3', b'1', b'2', b'3', b'5', b'9', b'6',
+            b'0', b'Z',
+        ];
+
+        let mut reader = untrusted::Reader::new(untrusted::Input::from(DER));
+        let time = Time::from_der(&mut reader).unwrap();
+        assert_eq!(
+            time,
+            time_from_ymdhms_utc(1996, 12, 31, 23, 59, 59).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_time_from_str() {
+        use core::str::FromStr;
+
+        use super::{time_from_ymdhms_utc, Error, Time};
+
+        assert_eq!(
+            Time::from_str("2023-06-01T12:00:00Z").unwrap(),
+            time_from_ymdhms_utc(2023, 6, 1, 12, 0, 0).unwrap()
+        );
+
+        // A fractional-seconds suffix is accepted and discarded.
+        assert_eq!(
+            Time::from_str("2023-06-01T12:00:00.123456Z").unwrap(),
+            time_from_ymdhms_utc(2023, 6, 1, 12, 0, 0).unwrap()
+        );
+
+        // Lowercase `t`/`z` separators are accepted, same as uppercase.
+        assert_eq!(
+            Time::from_str("2023-06-01t12:00:00z").unwrap(),
+            time_from_ymdhms_utc(2023, 6, 1, 12, 0, 0).unwrap()
+        );
+
+        // `Time::try_from(&str)` delegates to `FromStr`.
+        assert_eq!(
+            Time::try_from("2023-06-01T12:00:00Z").unwrap(),
+            time_from_ymdhms_utc(2023, 6, 1, 12, 0, 0).unwrap()
+        );
+
+        assert_eq!(
+            Time::from_str("2023-06-01T12:00:00"),
+            Err(Error::BadDerTime)
+        ); // missing Z
+        assert_eq!(
+            Time::from_str("2023-06-01 12:00:00Z"),
+            Err(Error::BadDerTime)
+        ); // missing T
+        assert_eq!(
+            Time::from_str("2023-13-01T12:00:00Z"),
+            Err(Error::BadDerTime)
+        ); // bad month
+        assert_eq!(Time::from_str("not-a-time"), Err(Error::BadDerTime));
+    }
+
+    #[test]
+    fn test_duration_arithmetic() {
+        use core::time::Duration;
+
+        use super::Time;
+
+        let t = Time::from_seconds_since_unix_epoch(1_000);
+        assert_eq!(
+            t + Duration::from_secs(10),
+            Time::from_seconds_since_unix_epoch(1_010)
+        );
+        assert_eq!(
+            t - Duration::from_secs(10),
+            Time::from_seconds_since_unix_epoch(990)
+        );
+
+        let earlier = Time::from_seconds_since_unix_epoch(900);
+        assert_eq!(t.duration_since(earlier), Some(Duration::from_secs(100)));
+        assert_eq!(earlier.duration_since(t), None);
+
+        assert!(earlier < t);
+    }
+
+    #[test]
+    fn test_to_ymdhms_utc_round_trip() {
+        use super::time_from_ymdhms_utc;
+
+        for &(year, month, day, hours, minutes, seconds) in &[
+            (1970, 1, 1, 0, 0, 0),
+            (1970, 1, 1, 0, 0, 1),
+            (1971, 1, 1, 0, 0, 0),
+            (2016, 12, 31, 23, 59, 59),
+            (2017, 1, 1, 0, 0, 0),
+            (2017, 4, 17, 17, 12, 42),
+            (2016, 4, 17, 17, 12, 42), // leap year, post-feb
+            (2000, 2, 29, 12, 0, 0),   // leap day
+            (2100, 3, 1, 0, 0, 0),     // not a leap year
+        ] {
+            let time = time_from_ymdhms_utc(year, month, day, hours, minutes, seconds).unwrap();
+            assert_eq!(
+                time.to_ymdhms_utc(),
+                (
+                    year,
+                    month as u8,
+                    day as u8,
+                    hours as u8,
+                    minutes as u8,
+                    seconds as u8
+                )
+            );
+        }
+    }
 }